@@ -1,13 +1,18 @@
 use anyhow::{anyhow, Context, Result};
-use futures::future::join_all;
+use futures::stream::{self, StreamExt};
 use image::DynamicImage;
 use image::io::Reader;
-use image::imageops::{resize, FilterType};
+use image::imageops::{crop_imm, resize, FilterType};
+use image::GenericImageView;
 use indicatif::{ProgressBar, ProgressStyle};
 use serde_derive::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet};
 use std::ffi::OsString;
-use std::path::PathBuf;
-use std::collections::HashSet;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 use tera;
 use tokio::fs::{copy, create_dir_all, read_to_string, write};
@@ -16,6 +21,174 @@ use walkdir::{DirEntry, WalkDir};
 
 static RIGAL_TOML: &str = "rigal.toml";
 
+fn default_jobs() -> usize {
+    num_cpus::get()
+}
+
+fn default_cache() -> PathBuf {
+    PathBuf::from(".rigal-cache")
+}
+
+/// Hashes the contents of `path` so variants can be cached under a key that is independent of
+/// the source file's location or mtime, and invalidated automatically when the bytes change.
+fn hash_contents(path: &Path) -> Result<String> {
+    let bytes = std::fs::read(path)?;
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+/// Lowercases `path`'s extension so cameras and phones that write uppercase extensions (e.g.
+/// `IMG_0001.CR2`, `.HEIC`) still match the lowercase-only extension sets built in `Builder::new`.
+fn lowercase_extension(path: &Path) -> Option<OsString> {
+    path.extension().and_then(|ext| ext.to_str()).map(|ext| OsString::from(ext.to_ascii_lowercase()))
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct Metadata {
+    captured_at: Option<String>,
+    camera: Option<String>,
+    lens: Option<String>,
+    exposure: Option<String>,
+    #[serde(skip)]
+    orientation: u16,
+}
+
+impl Default for Metadata {
+    fn default() -> Self {
+        Metadata {
+            captured_at: None,
+            camera: None,
+            lens: None,
+            exposure: None,
+            orientation: 1,
+        }
+    }
+}
+
+/// Reads capture timestamp, camera, lens, exposure and orientation out of `path`'s EXIF data.
+/// Missing or unreadable EXIF is not an error; the image is simply treated as having no metadata.
+fn read_metadata(path: &Path) -> Metadata {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Metadata::default(),
+    };
+
+    let exif = match exif::Reader::new().read_from_container(&mut BufReader::new(file)) {
+        Ok(exif) => exif,
+        Err(_) => return Metadata::default(),
+    };
+
+    let field = |tag| exif.get_field(tag, exif::In::PRIMARY).map(|field| field.display_value().to_string());
+
+    let orientation = exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .map_or(1, |value| value as u16);
+
+    Metadata {
+        captured_at: field(exif::Tag::DateTimeOriginal),
+        camera: field(exif::Tag::Model),
+        lens: field(exif::Tag::LensModel),
+        exposure: field(exif::Tag::ExposureTime),
+        orientation: orientation,
+    }
+}
+
+/// Auto-rotates `image` to match the EXIF orientation tag (1-8) so previews come out upright
+/// regardless of how the camera held the sensor.
+fn apply_orientation(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+#[cfg(feature = "raw")]
+static RAW_EXTENSIONS: &[&str] = &["cr2", "nef", "arw", "dng", "raf", "rw2", "orf"];
+
+#[cfg(feature = "heif")]
+static HEIF_EXTENSIONS: &[&str] = &["heic", "heif"];
+
+/// Whether `ext` (already lowercased) names a source format that `image`'s encoders cannot write
+/// back out, so `OutputFormat::Keep` must fall back to a renderable extension instead of reusing
+/// the source's own.
+fn is_unencodable_source(ext: &str) -> bool {
+    #[cfg(feature = "raw")]
+    if RAW_EXTENSIONS.contains(&ext) {
+        return true;
+    }
+
+    #[cfg(feature = "heif")]
+    if HEIF_EXTENSIONS.contains(&ext) {
+        return true;
+    }
+
+    false
+}
+
+/// Decodes `path` into a `DynamicImage`, routing camera RAW and HEIF/HEIC originals through
+/// dedicated decoders before falling back to the `image` crate for everything else.
+fn decode_image(path: &Path) -> Result<DynamicImage> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    #[cfg(feature = "raw")]
+    if RAW_EXTENSIONS.contains(&extension.as_str()) {
+        return decode_raw(path);
+    }
+
+    #[cfg(feature = "heif")]
+    if HEIF_EXTENSIONS.contains(&extension.as_str()) {
+        return decode_heif(path);
+    }
+
+    Ok(Reader::open(path)?.decode()?)
+}
+
+#[cfg(feature = "raw")]
+fn decode_raw(path: &Path) -> Result<DynamicImage> {
+    let decoded = imagepipe::simple_decode(path, 0, 0)
+        .map_err(|error| anyhow!("Could not decode RAW file `{}': {}", path.display(), error))?;
+
+    let buffer = image::RgbImage::from_raw(decoded.width as u32, decoded.height as u32, decoded.data)
+        .ok_or_else(|| anyhow!("Decoded RAW buffer for `{}' had an unexpected size", path.display()))?;
+
+    Ok(DynamicImage::ImageRgb8(buffer))
+}
+
+#[cfg(feature = "heif")]
+fn decode_heif(path: &Path) -> Result<DynamicImage> {
+    let file_path = path.to_str().ok_or_else(|| anyhow!("`{}' is not valid UTF-8", path.display()))?;
+
+    let context = libheif_rs::HeifContext::read_from_file(file_path)
+        .map_err(|error| anyhow!("Could not open HEIF file `{}': {}", path.display(), error))?;
+    let handle = context
+        .primary_image_handle()
+        .map_err(|error| anyhow!("Could not read HEIF image `{}': {}", path.display(), error))?;
+    let image = handle
+        .decode(libheif_rs::ColorSpace::Rgb(libheif_rs::RgbChroma::Rgb), false)
+        .map_err(|error| anyhow!("Could not decode HEIF image `{}': {}", path.display(), error))?;
+
+    let plane = image
+        .planes()
+        .interleaved
+        .ok_or_else(|| anyhow!("HEIF image `{}' has no interleaved RGB plane", path.display()))?;
+
+    let buffer = image::RgbImage::from_raw(plane.width, plane.height, plane.data.to_vec())
+        .ok_or_else(|| anyhow!("Decoded HEIF buffer for `{}' had an unexpected size", path.display()))?;
+
+    Ok(DynamicImage::ImageRgb8(buffer))
+}
+
 #[derive(StructOpt)]
 #[structopt(name = "rigal", about = "Static photo gallery generator")]
 enum Commands {
@@ -26,36 +199,132 @@ enum Commands {
     New,
 }
 
-#[derive(Serialize, Deserialize)]
-struct ThumbnailSize {
-    width: u32,
-    height: u32,
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum ResizeMode {
+    /// Resize to the exact configured dimensions, distorting the aspect ratio if necessary.
+    Scale,
+    /// Resize so the whole image fits inside the configured box, preserving the aspect ratio.
+    Fit,
+    /// Resize so the configured box is fully covered, then crop the overflow: horizontally
+    /// centered, vertically at the position chosen by `anchor`.
+    Fill,
 }
 
-#[derive(Serialize, Deserialize)]
-struct Resize {
+impl Default for ResizeMode {
+    fn default() -> Self {
+        ResizeMode::Scale
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Anchor {
+    Center,
+    Top,
+    Bottom,
+}
+
+impl Default for Anchor {
+    fn default() -> Self {
+        Anchor::Center
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+impl Default for SortDirection {
+    fn default() -> Self {
+        SortDirection::Ascending
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(tag = "format", rename_all = "snake_case")]
+enum OutputFormat {
+    /// Save using whatever format the output path's extension implies, copying untouched
+    /// sources that don't need resizing.
+    Keep,
+    Jpeg { quality: u8 },
+    WebP { quality: f32 },
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Keep
+    }
+}
+
+impl OutputFormat {
+    fn extension(&self) -> Option<&'static str> {
+        match self {
+            OutputFormat::Keep => None,
+            OutputFormat::Jpeg { .. } => Some("jpg"),
+            OutputFormat::WebP { .. } => Some("webp"),
+        }
+    }
+
+    /// Compact, filesystem-safe representation used to key the on-disk cache, so that changing
+    /// the output format or its quality invalidates stale cache entries instead of reusing them.
+    fn cache_key(&self) -> String {
+        match self {
+            OutputFormat::Keep => "keep".to_string(),
+            OutputFormat::Jpeg { quality } => format!("jpeg{}", quality),
+            OutputFormat::WebP { quality } => format!("webp{}", quality),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Size {
+    name: String,
     width: u32,
     height: u32,
+    #[serde(default)]
+    mode: ResizeMode,
+    #[serde(default)]
+    anchor: Anchor,
 }
 
 #[derive(Serialize, Deserialize)]
 struct Config {
     input: PathBuf,
     output: PathBuf,
-    thumbnail: ThumbnailSize,
-    resize: Option<Resize>,
+    sizes: Vec<Size>,
+    #[serde(default)]
+    output_format: OutputFormat,
+    #[serde(default = "default_jobs")]
+    jobs: usize,
+    #[serde(default = "default_cache")]
+    cache: PathBuf,
+    #[serde(default)]
+    sort: SortDirection,
 }
 
 #[derive(Debug)]
 struct Conversion {
     from: DirEntry,
-    to: PathBuf,
+    to_dir: PathBuf,
+    stem: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct ImageVariant {
+    url: String,
+    width: u32,
+    height: u32,
 }
 
 #[derive(Serialize, Debug)]
 struct Image {
-    image: String,
-    thumbnail: String,
+    name: String,
+    variants: BTreeMap<String, ImageVariant>,
+    metadata: Metadata,
 }
 
 #[derive(Serialize)]
@@ -74,13 +343,74 @@ struct Theme {
 struct Builder {
     config: Config,
     extensions: HashSet<OsString>,
+    output_extensions: HashSet<OsString>,
+    thumbnail_size: String,
     templates: tera::Tera,
 }
 
-fn resize_and_save(image: DynamicImage, width: u32, height: u32, path: PathBuf) -> Result<DynamicImage> {
-    let resized = resize(&image, width, height, FilterType::Lanczos3);
-    resized.save(path)?;
-    Ok(image)
+/// Encodes `image` to `path` using the configured output format. `Keep` defers to
+/// `DynamicImage::save`, which infers the format from `path`'s extension.
+fn encode_image(image: &DynamicImage, format: OutputFormat, path: &Path) -> Result<()> {
+    match format {
+        OutputFormat::Keep => {
+            image.save(path)?;
+        }
+        OutputFormat::Jpeg { quality } => {
+            let mut file = File::create(path)?;
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut file, quality)
+                .encode_image(image)?;
+        }
+        OutputFormat::WebP { quality } => {
+            let encoder = webp::Encoder::from_image(image)
+                .map_err(|error| anyhow!("Could not encode `{}' as WebP: {}", path.display(), error))?;
+            std::fs::write(path, &*encoder.encode(quality))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn resize_and_save(
+    image: DynamicImage,
+    width: u32,
+    height: u32,
+    mode: ResizeMode,
+    anchor: Anchor,
+    format: OutputFormat,
+    path: PathBuf,
+) -> Result<(u32, u32)> {
+    let (sw, sh) = image.dimensions();
+
+    let resized = match mode {
+        ResizeMode::Scale => resize(&image, width, height, FilterType::Lanczos3),
+        ResizeMode::Fit => {
+            let scale = (width as f64 / sw as f64).min(height as f64 / sh as f64);
+            let tw = ((sw as f64 * scale).round() as u32).max(1);
+            let th = ((sh as f64 * scale).round() as u32).max(1);
+            resize(&image, tw, th, FilterType::Lanczos3)
+        }
+        ResizeMode::Fill => {
+            let scale = (width as f64 / sw as f64).max(height as f64 / sh as f64);
+            let iw = ((sw as f64 * scale).round() as u32).max(1);
+            let ih = ((sh as f64 * scale).round() as u32).max(1);
+            let intermediate = resize(&image, iw, ih, FilterType::Lanczos3);
+
+            let cw = width.min(iw);
+            let ch = height.min(ih);
+            let x = (iw - cw) / 2;
+            let y = match anchor {
+                Anchor::Top => 0,
+                Anchor::Bottom => ih - ch,
+                Anchor::Center => (ih - ch) / 2,
+            };
+
+            crop_imm(&intermediate, x, y, cw, ch).to_image()
+        }
+    };
+
+    let dimensions = resized.dimensions();
+    encode_image(&DynamicImage::ImageRgba8(resized), format, &path)?;
+    Ok(dimensions)
 }
 
 impl Builder {
@@ -89,11 +419,34 @@ impl Builder {
             .context("Could not open `rigal.toml'.")?)
             .context("`rigal.toml' format seems broken.")?;
 
+        if config.jobs == 0 {
+            return Err(anyhow!("`jobs' must be at least 1, got 0."));
+        }
+
         let mut extensions: HashSet<OsString> = HashSet::new();
         let mut ext = OsString::new();
         ext.push("jpg");
         extensions.insert(ext);
 
+        #[cfg(feature = "raw")]
+        extensions.extend(RAW_EXTENSIONS.iter().map(|ext| OsString::from(*ext)));
+
+        #[cfg(feature = "heif")]
+        extensions.extend(HEIF_EXTENSIONS.iter().map(|ext| OsString::from(*ext)));
+
+        let output_extensions: HashSet<OsString> = match config.output_format.extension() {
+            Some(ext) => {
+                let mut output_extensions = HashSet::new();
+                output_extensions.insert(OsString::from(ext));
+                output_extensions
+            }
+            None => extensions.clone(),
+        };
+
+        let thumbnail_size = config.sizes.iter().find(|size| size.name == "thumb")
+            .or_else(|| config.sizes.get(0))
+            .map_or(String::new(), |size| size.name.clone());
+
         let mut templates = tera::Tera::new("_theme/templates/*.html")?;
 
         // We disable autoescape because we will dump a lot of path-like strings which will have to
@@ -103,61 +456,119 @@ impl Builder {
         Ok(Builder {
             config: config,
             extensions: extensions,
+            output_extensions: output_extensions,
+            thumbnail_size: thumbnail_size,
             templates: templates,
         })
     }
 
-    fn into_conversion(&self, entry: DirEntry) -> Result<Option<Conversion>> {
+    fn into_conversion(&self, entry: DirEntry) -> Result<Conversion> {
         let prefix = entry
             .path()
             .iter()
             .next()
             .ok_or(anyhow!("Cannot process current directory"))?;
 
-        let path = self.config.output.join(entry.path().strip_prefix(prefix)?);
+        let to = self.config.output.join(entry.path().strip_prefix(prefix)?);
 
-        if !path.exists() {
-            return Ok(Some(Conversion { from: entry, to: path }))
-        }
+        let to_dir = to.parent().map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."));
+        let stem = to.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().to_string());
 
-        if entry.metadata()?.modified()? > path.metadata()?.modified()? {
-            return Ok(Some(Conversion { from: entry, to: path }))
-        }
+        Ok(Conversion { from: entry, to_dir, stem })
+    }
 
-        Ok(None)
+    /// Maps an output album directory and image stem back to the original source file so
+    /// `write_template` can pull EXIF metadata that isn't stored alongside the rendered variants.
+    fn source_path_for(&self, output_dir: &Path, stem: &str) -> Option<PathBuf> {
+        let relative = output_dir.strip_prefix(&self.config.output).ok()?;
+        let input_dir = self.config.input.join(relative);
+
+        input_dir.read_dir().ok()?.filter_map(Result::ok).find(|entry| {
+            let path = entry.path();
+            path.file_stem().map_or(false, |file_stem| file_stem == stem)
+                && lowercase_extension(&path).map_or(false, |ext| self.extensions.contains(&ext))
+        }).map(|entry| entry.path())
     }
 
-    async fn process_image(&self, entry: Conversion, progress_bar: &ProgressBar) -> Result<()> {
-        let mut thumbnail_path = PathBuf::from(&entry.to);
-        thumbnail_path.pop();
-        thumbnail_path.push("thumbnails");
+    /// Renders one variant of `entry` for `size`, reusing the content-addressed cache entry for
+    /// `(source hash, width, height, mode, anchor, output format)` when it already exists so
+    /// unchanged images are skipped even if the rest of the config (output directory, other
+    /// sizes) has changed since.
+    async fn process_variant(&self, entry: &Conversion, source_hash: &str, size: &Size) -> Result<ImageVariant> {
+        let format = self.config.output_format;
+        let source_ext = entry.from.path().extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase());
+        let copyable_source = source_ext.as_deref().map_or(false, |ext| !is_unencodable_source(ext));
+
+        let ext = format.extension()
+            .map(String::from)
+            .or_else(|| source_ext.filter(|ext| !is_unencodable_source(ext)))
+            .unwrap_or_else(|| "jpg".to_string());
+
+        let cache_path = self.config.cache.join(format!(
+            "{}-{}x{}-{:?}-{:?}-{}.{}",
+            source_hash, size.width, size.height, size.mode, size.anchor, format.cache_key(), ext
+        ));
+        let output_path = entry.to_dir.join(format!("{}-{}.{}", entry.stem, size.name, ext));
 
-        if !thumbnail_path.exists() {
-            create_dir_all(&thumbnail_path).await?;
+        let dimensions = if cache_path.exists() {
+            image::image_dimensions(&cache_path)?
         }
+        else {
+            let source_path = entry.from.path().to_path_buf();
+            let (width, height, mode, anchor) = (size.width, size.height, size.mode, size.anchor);
+            let cache_target = cache_path.clone();
+
+            // Write under a per-thread temp name and rename into place atomically: two identical
+            // source files (duplicates or symlinks) hash to the same cache key and may be
+            // processed concurrently on different blocking threads, so writing `cache_target`
+            // directly would let one task's partial write corrupt the other's. The temp name
+            // keeps the real extension so `Keep` can still infer the format from it.
+            spawn_blocking(move || -> Result<(u32, u32)> {
+                let temp_path = cache_target.with_extension(format!("tmp-{:?}.{}", std::thread::current().id(), ext));
+
+                // `Keep` at a size that already matches the source's own dimensions doesn't need
+                // to round-trip through the decoder and re-encoder at all; just copy the bytes.
+                // RAW/HEIF sources are always decoded, since their bytes are not renderable.
+                if let (OutputFormat::Keep, true) = (format, copyable_source) {
+                    let source_dimensions = image::image_dimensions(&source_path)?;
+
+                    if source_dimensions == (width, height) {
+                        std::fs::copy(&source_path, &temp_path)?;
+                        std::fs::rename(&temp_path, &cache_target)?;
+                        return Ok(source_dimensions);
+                    }
+                }
 
-        thumbnail_path.push(entry.to.file_name().unwrap());
-
-        let image = Reader::open(entry.from.path())?.decode()?;
-        let width = self.config.thumbnail.width;
-        let height = self.config.thumbnail.height;
+                let image = decode_image(&source_path)?;
+                let orientation = read_metadata(&source_path).orientation;
+                let image = apply_orientation(image, orientation);
+                let dimensions = resize_and_save(image, width, height, mode, anchor, format, temp_path.clone())?;
+                std::fs::rename(&temp_path, &cache_target)?;
+                Ok(dimensions)
+            }).await??
+        };
 
-        let image = spawn_blocking(move || -> Result<DynamicImage> {
-            resize_and_save(image, width, height, thumbnail_path)
-        }).await??;
+        copy(&cache_path, &output_path).await?;
 
-        if let Some(resize_config) = &self.config.resize {
-            // User asks for resizing the source images, so lets do that.
-            let width = resize_config.width;
-            let height  = resize_config.height;
+        Ok(ImageVariant {
+            url: output_path.file_name().unwrap().to_string_lossy().to_string(),
+            width: dimensions.0,
+            height: dimensions.1,
+        })
+    }
 
-            spawn_blocking(move || -> Result<DynamicImage> {
-                resize_and_save(image, width, height, entry.to)
-            }).await??;
+    async fn process_image(&self, entry: Conversion, progress_bar: &ProgressBar) -> Result<()> {
+        if !entry.to_dir.exists() {
+            create_dir_all(&entry.to_dir).await?;
         }
-        else {
-            // No resizing required, just copy the source file.
-            copy(entry.from.path(), &entry.to).await?;
+
+        create_dir_all(&self.config.cache).await?;
+
+        let source_path = entry.from.path().to_path_buf();
+        let source_hash = spawn_blocking(move || hash_contents(&source_path)).await??;
+
+        for size in &self.config.sizes {
+            self.process_variant(&entry, &source_hash, size).await?;
         }
 
         progress_bar.inc(1);
@@ -166,30 +577,43 @@ impl Builder {
     }
 
     async fn process_images(&self) -> Result<()> {
-        // Find all images that are not directories, match a supported file extension and whose output
-        // either does not exist or is older than the source.
+        // Find all images that are not directories and match a supported file extension. Which
+        // variants actually need (re-)rendering is then decided per-size by the content-addressed
+        // cache in `process_variant`.
         let entries: Vec<_> = WalkDir::new(&self.config.input)
             .follow_links(true)
             .into_iter()
             .filter_map(Result::ok)
-            .filter(|e| e.path().is_file() && e.path().extension().map_or(false, |ext| self.extensions.contains(ext)))
+            .filter(|e| e.path().is_file() && lowercase_extension(e.path()).map_or(false, |ext| self.extensions.contains(&ext)))
             .map(|e| self.into_conversion(e))
             .filter_map(Result::ok)
-            .filter_map(|e| e)
             .collect();
 
-        let progress_bar = ProgressBar::new(entries.len() as u64);
+        let total = entries.len();
+
+        let progress_bar = ProgressBar::new(total as u64);
 
         progress_bar.set_style(ProgressStyle::default_bar().template(
                 "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {msg}",
         ));
 
-        let futures: Vec<_> = entries
-            .into_iter()
+        // Cap how many images decode/resize concurrently instead of spawning them all at once,
+        // which thrashes the blocking pool and memory on large libraries.
+        let results: Vec<Result<()>> = stream::iter(entries)
             .map(|e| self.process_image(e, &progress_bar))
-            .collect();
+            .buffer_unordered(self.config.jobs)
+            .collect()
+            .await;
 
-        join_all(futures).await;
+        let failures: Vec<_> = results.into_iter().filter_map(Result::err).collect();
+
+        if !failures.is_empty() {
+            for failure in &failures {
+                eprintln!("error: {:#}", failure);
+            }
+
+            return Err(anyhow!("{} of {} images failed to process", failures.len(), total));
+        }
 
         Ok(())
     }
@@ -232,19 +656,66 @@ impl Builder {
 
         let albums: Vec<_> = children
             .iter()
-            .filter(|e| e.path().is_dir() && e.file_name() != "thumbnails")
+            .filter(|e| e.path().is_dir())
             .map(|e| format!("{}/", e.path().strip_prefix(&self.config.output).unwrap().file_name().unwrap().to_string_lossy()))
             .collect();
 
-        let images: Vec<_> = children
+        let size_names: HashSet<&str> = self.config.sizes.iter().map(|size| size.name.as_str()).collect();
+
+        // Each source image is rendered as one `<stem>-<size name>.<ext>` file per configured
+        // size; group them back together here so each gallery entry carries every variant.
+        let mut variants_by_stem: BTreeMap<String, BTreeMap<String, ImageVariant>> = BTreeMap::new();
+
+        for child in children
             .iter()
-            .filter(|e| e.path().is_file() && e.path().extension().map_or(false, |ext| self.extensions.contains(ext)))
-            .map(|e| Image {
-                image: e.path().file_name().unwrap().to_string_lossy().to_string(),
-                thumbnail: PathBuf::from("thumbnails").join(e.path().file_name().unwrap()).to_string_lossy().to_string(),
+            .filter(|e| e.path().is_file() && lowercase_extension(&e.path()).map_or(false, |ext| self.output_extensions.contains(&ext))) {
+            let path = child.path();
+            let file_stem = path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+
+            let split = match file_stem.rsplit_once('-') {
+                Some(split) => split,
+                None => continue,
+            };
+
+            let (stem, size_name) = split;
+
+            if !size_names.contains(size_name) {
+                continue;
+            }
+
+            let (width, height) = image::image_dimensions(&path)?;
+
+            variants_by_stem.entry(stem.to_string()).or_default().insert(size_name.to_string(), ImageVariant {
+                url: path.file_name().unwrap().to_string_lossy().to_string(),
+                width: width,
+                height: height,
+            });
+        }
+
+        let mut images: Vec<_> = variants_by_stem
+            .into_iter()
+            .map(|(name, variants)| {
+                let metadata = self.source_path_for(entry.path(), &name)
+                    .map_or(Metadata::default(), |path| read_metadata(&path));
+
+                Image { name, variants, metadata }
             })
             .collect();
 
+        // Images without a readable `DateTimeOriginal` (screenshots, scans, EXIF-stripped
+        // uploads) have no meaningful capture time to compare, so they're placed after every
+        // dated image instead of colliding on `None` and collapsing to the front; their relative
+        // order among themselves falls back to the stable stem order the `BTreeMap` above built.
+        let descending = self.config.sort == SortDirection::Descending;
+
+        images.sort_by(|a, b| match (&a.metadata.captured_at, &b.metadata.captured_at) {
+            (Some(a), Some(b)) if descending => b.cmp(a),
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+
         let mut static_path = PathBuf::new();
 
         for _ in 0..entry.path().iter().count() - 1 {
@@ -262,7 +733,7 @@ impl Builder {
         context.insert("album", &Album {
             title: format!("{}", entry.file_name().to_string_lossy()),
             albums: albums,
-            thumbnail: images.get(0).map_or(None, |image| Some(image.thumbnail.clone())),
+            thumbnail: images.get(0).and_then(|image| image.variants.get(&self.thumbnail_size)).map(|variant| variant.url.clone()),
             images: images,
         });
 
@@ -276,7 +747,7 @@ impl Builder {
     async fn write_templates(&self) -> Result<()> {
         fn must_skip(entry: &DirEntry) -> bool {
             entry.file_type().is_file() ||
-                (entry.file_type().is_dir() && (entry.file_name() == "thumbnails" || entry.file_name() == "static"))
+                (entry.file_type().is_dir() && entry.file_name() == "static")
         }
 
         for entry in WalkDir::new(&self.config.output)
@@ -295,11 +766,19 @@ async fn create_config() -> Result<()> {
     let config = Config {
         input: PathBuf::from("input"),
         output: PathBuf::from("_build"),
-        thumbnail: ThumbnailSize {
-            width: 450,
-            height: 300,
-        },
-        resize: None,
+        sizes: vec![
+            Size {
+                name: String::from("thumb"),
+                width: 450,
+                height: 300,
+                mode: ResizeMode::default(),
+                anchor: Anchor::default(),
+            },
+        ],
+        output_format: OutputFormat::default(),
+        jobs: default_jobs(),
+        cache: default_cache(),
+        sort: SortDirection::default(),
     };
 
     write(PathBuf::from(RIGAL_TOML), toml::to_string(&config)?).await?;